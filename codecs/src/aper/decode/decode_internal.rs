@@ -1,4 +1,17 @@
 //! Internal decode functions.
+//!
+//! Every function here reads through [`AperCodecData`]'s bit primitives, so
+//! when `data` is in streaming mode (built with [`AperCodecData::empty`] and
+//! fed via [`AperCodecData::append`]) a short buffer surfaces as
+//! `Err(AperCodecError::Incomplete { .. })` instead of an out-of-range
+//! failure. The read that ran short leaves the cursor where it was, but reads
+//! that already succeeded earlier in the same call are not undone. So a call
+//! whose only read after an alignment is the one that ran short (for example
+//! `decode_constrained_whole_number` for ranges below 65536) can be retried
+//! as is once more bytes arrive, and `decode_unconstrained_length_determinent`
+//! resumes through the `LengthDeterminantProgress` passed back into it; the
+//! other functions here that make several reads do not rewind the cursor
+//! after an `Incomplete`.
 
 use crate::aper::AperCodecData;
 use crate::aper::AperCodecError;
@@ -9,83 +22,175 @@ use crate::aper::AperCodecError;
 // TODO: Support for the case when the length is greater than 64. We almost never come across this
 // case in practice, so right now it just Errors, if in real life we actually see this error for
 // any time it might have to be implemented to take care of that case.
-pub(super) fn decode_normally_small_length_determinent(
+pub(crate) fn decode_normally_small_length_determinent(
     data: &mut AperCodecData,
 ) -> Result<usize, AperCodecError> {
     let is_small = data.decode_bool()?;
     if !is_small {
         Ok(data.decode_bits_as_integer(6)? as usize + 1_usize)
     } else {
-        decode_unconstrained_length_determinent(data)
+        // The bitmap bits themselves are read by the caller using the returned count, so there
+        // are no items for us to consume here.
+        let mut progress = LengthDeterminantProgress::default();
+        decode_unconstrained_length_determinent(data, &mut progress, |_| Ok(()))
     }
 }
 
-pub(super) fn decode_constrained_length_detereminent(
+// Section 10.9 X.691.
+//
+// For ranges below 65536 this is just a constrained whole number. For ranges 65536 and above, the
+// standard falls back to the same self-delimiting general length determinant used by the
+// unconstrained case (see `decode_unconstrained_length_determinent`), fragments and all, instead
+// of a fixed-width field.
+pub(crate) fn decode_constrained_length_detereminent(
     data: &mut AperCodecData,
     lb: usize,
     ub: usize,
+    mut decode_item: impl FnMut(&mut AperCodecData) -> Result<(), AperCodecError>,
 ) -> Result<usize, AperCodecError> {
     let range = ub - lb + 1;
 
     if range < 65536 {
         // Almost always for our use cases, so let's just use it.
         let length = decode_constrained_whole_number(data, lb as i128, ub as i128)?;
-        eprintln!("length : {}", length);
+        for _ in 0..length {
+            decode_item(data)?;
+        }
         Ok(length as usize)
     } else {
-        unimplemented!("Lengths larger than 65536 are not supported yet.")
+        let mut progress = LengthDeterminantProgress::default();
+        decode_unconstrained_length_determinent(data, &mut progress, decode_item)
+    }
+}
+
+/// Resumable progress through one general length determinant (§10.9 X.691):
+/// a shape byte (or continuation byte), then the items it counts, possibly
+/// repeated across several `m * 16384`-item fragments.
+///
+/// Mirrors [`super::super::records::RecordsDecoder`]'s internal `Segment`
+/// state machine: a caller that wants to survive an `Incomplete` from a
+/// streaming [`AperCodecData`] keeps one of these alive across retries and
+/// passes the *same* instance back into
+/// [`decode_unconstrained_length_determinent`] each time, so the next call
+/// resumes exactly where the last one left off instead of re-reading the
+/// shape byte and corrupting an already-partially-consumed fragment.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LengthDeterminantProgress {
+    total: usize,
+    segment: Segment,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    /// The segment's own length determinant hasn't been read yet.
+    Pending,
+    /// A `10` prefix was read; `high` holds the 6 high bits of the 14-bit
+    /// length, and one more byte (the low 8 bits) is still needed.
+    PendingLength14 { high: usize },
+    /// `n` items left to read in a final (non-fragment) segment; once
+    /// they're all read the whole determinant is done.
+    Final(usize),
+    /// `n` items left to read in a fragment segment; once they're all read
+    /// another length determinant follows.
+    Fragment(usize),
+}
+
+impl Default for Segment {
+    fn default() -> Self {
+        Segment::Pending
     }
 }
 
-pub(super) fn decode_unconstrained_length_determinent(
+// Section 10.9 X.691: the general length determinant.
+//
+// After aligning, a leading `0` bit means the next 7 bits are the final length (0..127); a
+// leading `10` means the next 14 bits are the final length (0..16383); a leading `11` means the
+// next 6 bits give a multiplier `m` in 1..4, and a fragment of exactly `m * 16384` items follows,
+// after which another length determinant must be read for the remainder, repeating until a
+// non-`11` (final, possibly zero) determinant terminates. `decode_item` is called once per item,
+// in between fragments, so each fragment's items are actually consumed from the stream before the
+// next fragment's own length determinant is read. Returns the total number of items.
+//
+// The shape byte is always read as one atomic `decode_bits_as_integer(8)` (rather than a
+// separate bool-then-bits read), and each item is only counted in `progress` once it has actually
+// been decoded, so an `Incomplete` partway through a fragment leaves `progress` exactly where it
+// was: the caller appends more data and calls this again with the same `progress` to resume,
+// instead of restarting from `decode_align` and reinterpreting already-consumed item bytes as a
+// new shape byte.
+pub(crate) fn decode_unconstrained_length_determinent(
     data: &mut AperCodecData,
+    progress: &mut LengthDeterminantProgress,
+    mut decode_item: impl FnMut(&mut AperCodecData) -> Result<(), AperCodecError>,
 ) -> Result<usize, AperCodecError> {
-    let _ = data.decode_align()?;
-    let first = data.decode_bool()?;
-    let length = if !first {
-        data.decode_bits_as_integer(7)?
-    } else {
-        let second = data.decode_bool()?;
-        if second {
-            data.decode_bits_as_integer(14)?
-        } else {
-            let length = data.decode_bits_as_integer(6)?;
-            if length > 4 || length < 1 {
-                return Err(AperCodecError::new("The value should be 1 to 4"));
-            } else {
-                length * 16384
+    loop {
+        let LengthDeterminantProgress { total, segment } = progress;
+        match segment {
+            Segment::Pending => {
+                data.decode_align()?;
+                let shape = data.decode_bits_as_integer(8)? as usize;
+                *segment = if shape & 0x80 == 0 {
+                    Segment::Final(shape & 0x7F)
+                } else if shape & 0x40 == 0 {
+                    Segment::PendingLength14 {
+                        high: shape & 0x3F,
+                    }
+                } else {
+                    let m = shape & 0x3F;
+                    if !(1..=4).contains(&m) {
+                        return Err(AperCodecError::new(
+                            "Length determinant fragment multiplier must be 1 to 4.",
+                        ));
+                    }
+                    Segment::Fragment(m * 16384)
+                };
+            }
+            Segment::PendingLength14 { high } => {
+                let low = data.decode_bits_as_integer(8)? as usize;
+                *segment = Segment::Final((*high << 8) | low);
+            }
+            Segment::Final(0) => return Ok(*total),
+            Segment::Fragment(0) => *segment = Segment::Pending,
+            Segment::Final(n) | Segment::Fragment(n) => {
+                decode_item(data)?;
+                *n -= 1;
+                *total += 1;
             }
         }
-    };
-    Ok(length as usize)
+    }
 }
 
 // Section 10.8 X.691
-pub(super) fn decode_unconstrained_whole_number(
+pub(crate) fn decode_unconstrained_whole_number(
     data: &mut AperCodecData,
 ) -> Result<i128, AperCodecError> {
-    let length = decode_unconstrained_length_determinent(data)?;
-    eprintln!("unconstrained length: {}", length);
-    let bits = length * 8;
-    data.decode_bits_as_integer(bits)
+    let mut bytes = Vec::new();
+    let mut progress = LengthDeterminantProgress::default();
+    let _ = decode_unconstrained_length_determinent(data, &mut progress, |data| {
+        bytes.push(data.decode_bits_as_integer(8)? as u8);
+        Ok(())
+    })?;
+    Ok(bytes.iter().fold(0_i128, |acc, b| (acc << 8) | *b as i128))
 }
 
 // Section 10.7 X.691
-pub(super) fn decode_semi_constrained_whole_number(
+pub(crate) fn decode_semi_constrained_whole_number(
     data: &mut AperCodecData,
     lb: i128,
 ) -> Result<i128, AperCodecError> {
-    let length = decode_unconstrained_length_determinent(data)?;
-    eprintln!("unconstrained length: {}", length);
-    let bits = length * 8;
-    let val = data.decode_bits_as_integer(bits)?;
+    let mut bytes = Vec::new();
+    let mut progress = LengthDeterminantProgress::default();
+    let _ = decode_unconstrained_length_determinent(data, &mut progress, |data| {
+        bytes.push(data.decode_bits_as_integer(8)? as u8);
+        Ok(())
+    })?;
+    let val = bytes.iter().fold(0_i128, |acc, b| (acc << 8) | *b as i128);
     Ok(val + lb)
 }
 
 // Decode a 'constrained' whole number where both `lb` and `ub` are available.
 //
 // From Section 10.5
-pub(super) fn decode_constrained_whole_number(
+pub(crate) fn decode_constrained_whole_number(
     data: &mut AperCodecData,
     lb: i128,
     ub: i128,
@@ -119,7 +224,11 @@ pub(super) fn decode_constrained_whole_number(
         } else {
             let bytes_needed = bytes_needed_for_range(range);
             eprintln!("bytes_needed : {}", bytes_needed);
-            let length = decode_constrained_length_detereminent(data, 1, bytes_needed as usize)?;
+            // We only want the length value itself here; the bytes it counts are read below in
+            // one contiguous read, so there is nothing for this length determinant to hand off
+            // to a per-item decode.
+            let length =
+                decode_constrained_length_detereminent(data, 1, bytes_needed as usize, |_| Ok(()))?;
             let bits = (length + 1) * 8;
             let _ = data.decode_align()?;
             data.decode_bits_as_integer(bits)?
@@ -196,4 +305,94 @@ mod tests {
         let value = value.unwrap();
         assert_eq!(value, 16843010_i128);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_decode_constrained_whole_number_incomplete_then_resumes() {
+        // Only the first byte of a 2-byte (range 256..65536) encoding has arrived so far.
+        let mut codec_data = AperCodecData::empty();
+        codec_data.append(&[0x00u8]);
+
+        let err = decode_constrained_whole_number(&mut codec_data, 0, 64000).unwrap_err();
+        assert_eq!(err, AperCodecError::Incomplete { needed_bits: 8 });
+
+        // The cursor wasn't disturbed by the failed attempt, so appending the rest of the
+        // buffer and retrying the exact same call now succeeds.
+        codec_data.append(&[0x01u8]);
+        let value = decode_constrained_whole_number(&mut codec_data, 0, 64000);
+        assert!(value.is_ok(), "{:#?}", value.err());
+        assert_eq!(value.unwrap(), 1_i128);
+    }
+
+    #[test]
+    fn test_decode_unconstrained_length_determinent_fragmented() {
+        // 0xC1 = `11` (fragment marker) + multiplier `000001` = one 16384-item fragment, followed
+        // by a final determinant of `0` + `0000101` = 5 more items, for a total of 16389.
+        let mut data = vec![0xC1u8];
+        data.extend(std::iter::repeat(0xAAu8).take(16384));
+        data.push(0x05);
+        data.extend(std::iter::repeat(0xBBu8).take(5));
+
+        let mut codec_data = AperCodecData::from_slice(&data);
+        let mut progress = LengthDeterminantProgress::default();
+        let mut items = Vec::new();
+        let total = decode_unconstrained_length_determinent(&mut codec_data, &mut progress, |data| {
+            items.push(data.decode_bits_as_integer(8)? as u8);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(total, 16389);
+        assert_eq!(items.len(), 16389);
+        assert!(items[..16384].iter().all(|b| *b == 0xAA));
+        assert!(items[16384..].iter().all(|b| *b == 0xBB));
+    }
+
+    #[test]
+    fn test_decode_unconstrained_length_determinent_resumes_mid_fragment() {
+        // `0xC1` = `11` (fragment marker) + multiplier `000001` = one 16384-item fragment; only
+        // the shape byte and the first 3 fragment items have arrived so far.
+        let mut codec_data = AperCodecData::empty();
+        codec_data.append(&[0xC1u8, 0xAA, 0xAA, 0xAA]);
+
+        let mut progress = LengthDeterminantProgress::default();
+        let mut items = Vec::new();
+
+        let err = decode_unconstrained_length_determinent(&mut codec_data, &mut progress, |data| {
+            items.push(data.decode_bits_as_integer(8)? as u8);
+            Ok(())
+        })
+        .unwrap_err();
+        assert_eq!(err, AperCodecError::Incomplete { needed_bits: 8 });
+        assert_eq!(items.len(), 3);
+
+        // Retrying from scratch with a fresh `LengthDeterminantProgress` would reinterpret the
+        // next fragment byte as a brand new shape byte; resuming with the same `progress` instead
+        // picks up exactly where the fragment's item loop left off.
+        codec_data.append(&[0xAA]);
+        let err = decode_unconstrained_length_determinent(&mut codec_data, &mut progress, |data| {
+            items.push(data.decode_bits_as_integer(8)? as u8);
+            Ok(())
+        })
+        .unwrap_err();
+        assert_eq!(err, AperCodecError::Incomplete { needed_bits: 8 });
+        assert_eq!(items.len(), 4);
+        assert!(items.iter().all(|b| *b == 0xAA));
+    }
+
+    #[test]
+    fn test_decode_constrained_length_detereminent_ge_65536_is_fragmented() {
+        // Range is exactly 65536 (0..=65535), which must fall back to the general length
+        // determinant rather than a fixed-width field. `0x03` = `0` + `0000011` = 3 items.
+        let data = &[0x03u8, 0x01, 0x02, 0x03];
+        let mut codec_data = AperCodecData::from_slice(data);
+        let mut items = Vec::new();
+        let total = decode_constrained_length_detereminent(&mut codec_data, 0, 65535, |data| {
+            items.push(data.decode_bits_as_integer(8)? as u8);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(total, 3);
+        assert_eq!(items, vec![0x01, 0x02, 0x03]);
+    }
+}
@@ -0,0 +1,5 @@
+//! Decode helpers shared by the `AperCodec` derive macro and hand-written codecs.
+
+mod decode_internal;
+
+pub(crate) use decode_internal::*;
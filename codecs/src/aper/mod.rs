@@ -0,0 +1,344 @@
+//! Aligned Packed Encoding Rules (APER) codec support, shared by the
+//! hand-written decode helpers and the `AperCodec` derive macro.
+
+mod decode;
+pub mod records;
+
+pub(crate) use decode::*;
+
+/// Errors that can occur while decoding an APER-encoded value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AperCodecError {
+    /// The value was present but invalid (bad range, unknown choice index, ...).
+    Error(String),
+    /// Not enough bits were buffered to finish this decode. `needed_bits` is how
+    /// many *more* bits (beyond what is currently available) are required
+    /// before the same decode call can be retried. The read that ran short
+    /// leaves the cursor exactly where it was, so once a caller has appended
+    /// more data (see [`AperCodecData::append`]) it can simply call the same
+    /// [`AperCodecData`] read again. A decode made of several reads keeps the
+    /// reads that succeeded before it; [`records::RecordsDecoder::next_record`]
+    /// resumes from its own saved state, while
+    /// [`records::decode_protocol_ie_container`] keeps no state across an
+    /// `Incomplete`.
+    Incomplete { needed_bits: usize },
+}
+
+impl AperCodecError {
+    pub fn new(message: impl Into<String>) -> Self {
+        AperCodecError::Error(message.into())
+    }
+}
+
+impl std::fmt::Display for AperCodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AperCodecError::Error(message) => write!(f, "{}", message),
+            AperCodecError::Incomplete { needed_bits } => {
+                write!(f, "Incomplete: {} more bit(s) needed", needed_bits)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AperCodecError {}
+
+/// A bit-level cursor over an APER encoded buffer.
+///
+/// `AperCodecData` can be used in two modes:
+///
+/// - "Whole buffer" mode, via [`AperCodecData::from_slice`], where the entire
+///   encoded message is already available (the common case for decoding a
+///   message read fully off disk or out of a datagram).
+/// - Streaming mode, via [`AperCodecData::empty`] plus repeated
+///   [`AperCodecData::append`] calls, for data arriving incrementally off a
+///   stream socket. In this mode a decode that runs past the end of the
+///   currently buffered bits returns `Err(AperCodecError::Incomplete { .. })`
+///   rather than a hard failure, and never advances the cursor, so the exact
+///   same decode call can be retried once more bytes have been appended.
+///
+/// The two modes are distinguished by the `complete` flag set at
+/// construction: a buffer built from [`Self::from_slice`] is known to hold
+/// the whole message, so running out of bits there is a genuine decode
+/// failure (an `Error`), not a reason to wait for more bytes that are never
+/// coming.
+#[derive(Debug, Clone)]
+pub struct AperCodecData {
+    bytes: Vec<u8>,
+    bit_cursor: usize,
+    /// `true` for a whole-buffer decode ([`Self::from_slice`]), `false` for a
+    /// streaming decode ([`Self::empty`] + [`Self::append`]).
+    complete: bool,
+}
+
+impl AperCodecData {
+    /// Wraps a complete, already-received buffer.
+    pub fn from_slice(data: &[u8]) -> Self {
+        AperCodecData {
+            bytes: data.to_vec(),
+            bit_cursor: 0,
+            complete: true,
+        }
+    }
+
+    /// Starts an empty streaming buffer. Feed bytes in with [`Self::append`] as
+    /// they arrive.
+    pub fn empty() -> Self {
+        AperCodecData {
+            bytes: vec![],
+            bit_cursor: 0,
+            complete: false,
+        }
+    }
+
+    /// Appends newly received bytes, typically after a decode call returned
+    /// `Incomplete`.
+    pub fn append(&mut self, more: &[u8]) {
+        self.bytes.extend_from_slice(more);
+    }
+
+    /// The current bit position, for callers that checkpoint/resume decoding
+    /// across a different `AperCodecData` instance.
+    pub fn bit_position(&self) -> usize {
+        self.bit_cursor
+    }
+
+    /// `true` if this buffer was built with [`Self::from_slice`] (the whole
+    /// message is already available), `false` if it's a streaming buffer
+    /// built with [`Self::empty`].
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    fn total_bits(&self) -> usize {
+        self.bytes.len() * 8
+    }
+
+    fn remaining_bits(&self) -> usize {
+        self.total_bits() - self.bit_cursor
+    }
+
+    fn bit_at(&self, index: usize) -> bool {
+        let byte = self.bytes[index / 8];
+        let shift = 7 - (index % 8);
+        (byte >> shift) & 1 == 1
+    }
+
+    /// Advances the cursor by `n` bits without reading them.
+    ///
+    /// If fewer than `n` bits remain: when `allow_incomplete` is `true` this
+    /// returns `Incomplete` and leaves the cursor untouched (used when
+    /// skipping bits a streaming caller may not have received yet); otherwise
+    /// it returns a hard `Error`.
+    pub fn advance_maybe_err(
+        &mut self,
+        n: usize,
+        allow_incomplete: bool,
+    ) -> Result<(), AperCodecError> {
+        if self.remaining_bits() < n {
+            let needed_bits = n - self.remaining_bits();
+            return if allow_incomplete {
+                Err(AperCodecError::Incomplete { needed_bits })
+            } else {
+                Err(AperCodecError::new("Not enough bits remaining to advance."))
+            };
+        }
+        self.bit_cursor += n;
+        Ok(())
+    }
+
+    /// Returns the `Incomplete`-or-`Error` result for a read that's short by
+    /// `needed_bits`, per [`Self::is_complete`]'s mode.
+    fn short_read_err(&self, needed_bits: usize, what: &str) -> AperCodecError {
+        if self.complete {
+            AperCodecError::new(format!("Not enough bits remaining to decode {}.", what))
+        } else {
+            AperCodecError::Incomplete { needed_bits }
+        }
+    }
+
+    /// Decodes a single bit as a `bool`.
+    pub fn decode_bool(&mut self) -> Result<bool, AperCodecError> {
+        if self.remaining_bits() < 1 {
+            return Err(self.short_read_err(1, "a bool"));
+        }
+        let value = self.bit_at(self.bit_cursor);
+        self.bit_cursor += 1;
+        Ok(value)
+    }
+
+    /// Decodes `n` bits as a big-endian unsigned integer.
+    pub fn decode_bits_as_integer(&mut self, n: usize) -> Result<i128, AperCodecError> {
+        if n == 0 {
+            return Ok(0);
+        }
+        if self.remaining_bits() < n {
+            let needed_bits = n - self.remaining_bits();
+            return Err(self.short_read_err(needed_bits, "an integer"));
+        }
+        let mut value: i128 = 0;
+        for i in 0..n {
+            value <<= 1;
+            if self.bit_at(self.bit_cursor + i) {
+                value |= 1;
+            }
+        }
+        self.bit_cursor += n;
+        Ok(value)
+    }
+
+    /// Aligns the cursor to the next byte boundary, as required before every
+    /// octet-aligned field in APER.
+    pub fn decode_align(&mut self) -> Result<(), AperCodecError> {
+        let misaligned = self.bit_cursor % 8;
+        if misaligned != 0 {
+            self.advance_maybe_err(8 - misaligned, !self.complete)?;
+        }
+        Ok(())
+    }
+
+    /// Reads `n` bits and converts them to `T`. This is a typed wrapper
+    /// around [`Self::decode_bits_as_integer`], for code (hand-written
+    /// codecs or the `AperCodec` derive) that wants a typed result instead of
+    /// always widening to `i128` and casting down itself.
+    pub fn take_bits<T>(&mut self, n: usize) -> Result<T, AperCodecError>
+    where
+        T: TryFrom<u128>,
+    {
+        let saved_cursor = self.bit_cursor;
+        let value = self.decode_bits_as_integer(n)?;
+        T::try_from(value as u128).map_err(|_| {
+            self.bit_cursor = saved_cursor;
+            AperCodecError::new(format!("{} bit value does not fit the requested type", n))
+        })
+    }
+
+    /// Like [`Self::take_bits`], but leaves the cursor untouched, so the
+    /// caller can inspect upcoming bits (a choice index, an extension bitmap
+    /// bit, ...) before deciding how much to actually consume.
+    pub fn peek_bits<T>(&mut self, n: usize) -> Result<T, AperCodecError>
+    where
+        T: TryFrom<u128>,
+    {
+        let saved_cursor = self.bit_cursor;
+        let result = self.take_bits(n);
+        self.bit_cursor = saved_cursor;
+        result
+    }
+
+    /// Aligns to the next byte boundary, then runs `f`. Shorthand for the
+    /// `decode_align()?` followed by a read that shows up throughout the APER
+    /// decode helpers.
+    pub fn align_then<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, AperCodecError>,
+    ) -> Result<T, AperCodecError> {
+        self.decode_align()?;
+        f(self)
+    }
+
+    /// Consumes `n` bits and verifies they equal `pattern`, e.g. the fixed
+    /// `0b10` prefix that marks a 14-bit length determinant. A mismatch is a
+    /// hard `Error` rather than `Incomplete` (assuming there are enough bits
+    /// to check in the first place), since "the shape is wrong" and "we
+    /// haven't received enough bytes yet" are different problems for a
+    /// caller to handle.
+    pub fn bit_tag(&mut self, pattern: u128, n: usize) -> Result<(), AperCodecError> {
+        let value: u128 = self.peek_bits(n)?;
+        if value != pattern {
+            return Err(AperCodecError::new(format!(
+                "Expected bit pattern {:#0width$b}, found {:#0width$b}",
+                pattern,
+                value,
+                width = n + 2
+            )));
+        }
+        self.bit_cursor += n;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_take_bits_typed() {
+        let mut data = AperCodecData::from_slice(&[0b1010_0000]);
+        let value: u8 = data.take_bits(4).unwrap();
+        assert_eq!(value, 0b1010);
+        assert_eq!(data.bit_position(), 4);
+    }
+
+    #[test]
+    fn test_take_bits_leaves_cursor_untouched_when_value_does_not_fit() {
+        // 16 bits decode to 511, which doesn't fit a `u8` (max 255); the bits were
+        // fully available, so the failure comes from `T::try_from`, not from running
+        // out of buffer.
+        let mut data = AperCodecData::from_slice(&[0x01u8, 0xFFu8]);
+        let err = data.take_bits::<u8>(16).unwrap_err();
+        assert_eq!(
+            err,
+            AperCodecError::Error("16 bit value does not fit the requested type".to_string())
+        );
+        assert_eq!(data.bit_position(), 0);
+    }
+
+    #[test]
+    fn test_peek_bits_does_not_advance() {
+        let mut data = AperCodecData::from_slice(&[0b1100_0000]);
+        let peeked: u8 = data.peek_bits(2).unwrap();
+        assert_eq!(peeked, 0b11);
+        assert_eq!(data.bit_position(), 0);
+
+        let taken: u8 = data.take_bits(2).unwrap();
+        assert_eq!(taken, peeked);
+        assert_eq!(data.bit_position(), 2);
+    }
+
+    #[test]
+    fn test_align_then_reads_the_next_byte() {
+        let mut data = AperCodecData::from_slice(&[0xFFu8, 0x07u8]);
+        data.advance_maybe_err(3, false).unwrap();
+        let value: u8 = data.align_then(|d| d.take_bits(8)).unwrap();
+        assert_eq!(value, 0x07);
+    }
+
+    #[test]
+    fn test_bit_tag_matches_and_advances() {
+        let mut data = AperCodecData::from_slice(&[0b1000_0000]);
+        data.bit_tag(0b10, 2).unwrap();
+        assert_eq!(data.bit_position(), 2);
+    }
+
+    #[test]
+    fn test_bit_tag_mismatch_is_an_error_not_incomplete() {
+        let mut data = AperCodecData::from_slice(&[0b0100_0000]);
+        let err = data.bit_tag(0b10, 2).unwrap_err();
+        assert_eq!(err, AperCodecError::Error(
+            "Expected bit pattern 0b10, found 0b01".to_string()
+        ));
+        // A failed tag check doesn't consume anything.
+        assert_eq!(data.bit_position(), 0);
+    }
+
+    #[test]
+    fn test_short_read_on_a_whole_buffer_is_a_hard_error_not_incomplete() {
+        // `from_slice` promises the whole message is present, so running past the
+        // end of it is a genuine decode failure rather than "wait for more bytes".
+        let mut data = AperCodecData::from_slice(&[0xFFu8]);
+        data.decode_bits_as_integer(8).unwrap();
+        let err = data.decode_bool().unwrap_err();
+        assert!(matches!(err, AperCodecError::Error(_)));
+    }
+
+    #[test]
+    fn test_short_read_on_a_streaming_buffer_is_incomplete() {
+        let mut data = AperCodecData::empty();
+        data.append(&[0xFFu8]);
+        data.decode_bits_as_integer(8).unwrap();
+        let err = data.decode_bool().unwrap_err();
+        assert_eq!(err, AperCodecError::Incomplete { needed_bits: 1 });
+    }
+}
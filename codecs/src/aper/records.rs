@@ -0,0 +1,365 @@
+//! Generic sequential-record (TLV) decoder for ProtocolIE containers.
+//!
+//! 3GPP PER messages are built out of repeated ProtocolIE records: an `id`
+//! (`ProtocolIE_ID`), a `Criticality`, and an open-type value. Rather than
+//! decode a whole container up front, [`RecordsDecoder`] reads the element
+//! count once and then yields one `(id, criticality, value_bytes)` triplet
+//! at a time. An `id` the caller doesn't recognise doesn't always fail the
+//! decode: [`decode_protocol_ie_container`] honours its `criticality` —
+//! `Ignore`/`Notify` carry it forward as [`MaybeParsed::Unparsed`] so
+//! messages with extension IEs from a newer peer still decode, while
+//! `Reject` fails the whole container.
+
+use super::decode::{decode_constrained_length_detereminent, decode_constrained_whole_number};
+use super::{AperCodecData, AperCodecError};
+
+/// A ProtocolIE identifier, encoded as an `INTEGER (0..65535)`.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProtocolIE_ID(pub u16);
+
+impl ProtocolIE_ID {
+    fn aper_decode(data: &mut AperCodecData) -> Result<Self, AperCodecError> {
+        // This range is decoded as a length followed by the value, so an `Incomplete` on the
+        // value would otherwise leave the length's bits consumed; rewind so a retry starts
+        // from the `id`'s first bit.
+        let saved_cursor = data.bit_cursor;
+        let value = decode_constrained_whole_number(data, 0, 65535).map_err(|e| {
+            data.bit_cursor = saved_cursor;
+            e
+        })?;
+        Ok(ProtocolIE_ID(value as u16))
+    }
+}
+
+/// How a receiver must treat an IE it doesn't understand or couldn't decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Criticality {
+    /// The whole procedure must be rejected.
+    Reject,
+    /// The IE is dropped; the rest of the message is still processed.
+    Ignore,
+    /// The IE is dropped, but the receiver should report that it happened.
+    Notify,
+}
+
+impl Criticality {
+    fn aper_decode(data: &mut AperCodecData) -> Result<Self, AperCodecError> {
+        let value = decode_constrained_whole_number(data, 0, 2)?;
+        match value {
+            0 => Ok(Criticality::Reject),
+            1 => Ok(Criticality::Ignore),
+            2 => Ok(Criticality::Notify),
+            // Two bits can also hold 3, which `decode_constrained_whole_number` does not reject.
+            _ => Err(AperCodecError::new(format!(
+                "Criticality value {} is out of range 0..2",
+                value
+            ))),
+        }
+    }
+
+    pub fn is_reject(&self) -> bool {
+        matches!(self, Criticality::Reject)
+    }
+
+    pub fn is_ignore(&self) -> bool {
+        matches!(self, Criticality::Ignore)
+    }
+
+    pub fn is_notify(&self) -> bool {
+        matches!(self, Criticality::Notify)
+    }
+}
+
+/// The result of decoding one ProtocolIE record: either the fully decoded
+/// value, or the raw bits of an `id` the caller doesn't recognise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaybeParsed<T> {
+    Parsed(T),
+    Unparsed {
+        id: ProtocolIE_ID,
+        criticality: Criticality,
+        raw_bits: Vec<u8>,
+    },
+}
+
+/// How far into the current record we'd gotten when a
+/// streaming [`AperCodecData`] last ran out of buffered bits.
+///
+/// A plain "call `next_record` again" contract doesn't work for a multi-field
+/// record: if `id` decoded successfully but `criticality` then hit
+/// `Incomplete`, the cursor is left sitting right after `id`'s bits, so
+/// blindly restarting from `id` would reinterpret `criticality`'s bits as a
+/// new `id`. Tracking which field we'd reached lets [`RecordsDecoder`] resume
+/// exactly there instead.
+enum InProgress {
+    /// Nothing decoded yet for the next record.
+    Start,
+    /// `id` is decoded; `criticality` still needs reading.
+    HaveId(ProtocolIE_ID),
+    /// `id` and `criticality` are decoded and the value has been aligned;
+    /// `raw_bits` holds the open-type bytes read so far and `segment` tracks
+    /// progress through the value's (possibly fragmented) general length
+    /// determinant.
+    ReadingValue {
+        id: ProtocolIE_ID,
+        criticality: Criticality,
+        raw_bits: Vec<u8>,
+        segment: Segment,
+    },
+}
+
+/// Progress through one general length determinant segment of the open-type
+/// value (see `decode_unconstrained_length_determinent`'s fragmentation
+/// rules). Like that helper's `LengthDeterminantProgress`, this tracks
+/// enough state across calls to resume a segment's byte loop after an
+/// `Incomplete` instead of restarting it.
+///
+/// The determinant's own shape byte is always read as one atomic
+/// `decode_bits_as_integer(8)`, so a `0xxxxxxx` (7-bit length) or `11xxxxxx`
+/// (fragment) prefix resolves in a single step; only the `10xxxxxx` (14-bit
+/// length) prefix needs a second byte, so its high 6 bits are parked in
+/// `PendingLength14` while that's awaited.
+enum Segment {
+    /// The segment's own length determinant hasn't been read yet.
+    Pending,
+    /// A `10` prefix was read; `high` holds the 6 high bits of the 14-bit
+    /// length, and one more byte (the low 8 bits) is still needed.
+    PendingLength14 { high: usize },
+    /// `n` bytes left to read in a final (non-fragment) segment; once they're
+    /// all read the whole value is done.
+    Final(usize),
+    /// `n` bytes left to read in a fragment segment; once they're all read
+    /// another length determinant follows.
+    Fragment(usize),
+}
+
+/// Decodes a ProtocolIE-Container as a lazy sequence of
+/// `(id, criticality, value_bytes)` triplets.
+///
+/// Construction reads the container's element count up front; each
+/// subsequent [`RecordsDecoder::next_record`] call (or `Iterator::next`)
+/// decodes exactly one element's `id` and `criticality`, then reads its
+/// open-type value as raw bytes for the caller to decode with whatever type
+/// that `id` maps to.
+///
+/// This composes with [`AperCodecData`]'s streaming mode: if a call returns
+/// `Err(AperCodecError::Incomplete { .. })`, use [`Self::data_mut`] to reach
+/// the underlying buffer, [`AperCodecData::append`] more bytes, then call
+/// [`Self::next_record`] again to resume from exactly where decoding left
+/// off (not from the start of the record).
+pub struct RecordsDecoder<'d> {
+    data: &'d mut AperCodecData,
+    remaining: usize,
+    in_progress: InProgress,
+}
+
+impl<'d> RecordsDecoder<'d> {
+    pub fn new(data: &'d mut AperCodecData) -> Result<Self, AperCodecError> {
+        // The records themselves are consumed one at a time by `next_record`, not by this length
+        // determinant, so there's no per-item decode to hand off here.
+        let count = decode_constrained_length_detereminent(data, 0, 65535, |_| Ok(()))?;
+        Ok(RecordsDecoder {
+            data,
+            remaining: count,
+            in_progress: InProgress::Start,
+        })
+    }
+
+    /// Reborrows the underlying buffer, so a streaming caller can
+    /// [`AperCodecData::append`] more bytes after `next_record` returns
+    /// `Incomplete` without giving up this decoder's in-progress state.
+    pub fn data_mut(&mut self) -> &mut AperCodecData {
+        self.data
+    }
+
+    pub fn next_record(
+        &mut self,
+    ) -> Result<Option<(ProtocolIE_ID, Criticality, Vec<u8>)>, AperCodecError> {
+        loop {
+            match &mut self.in_progress {
+                InProgress::Start => {
+                    if self.remaining == 0 {
+                        return Ok(None);
+                    }
+                    let id = ProtocolIE_ID::aper_decode(self.data)?;
+                    self.in_progress = InProgress::HaveId(id);
+                }
+                InProgress::HaveId(id) => {
+                    let id = *id;
+                    let criticality = Criticality::aper_decode(self.data)?;
+                    self.data.decode_align()?;
+                    self.in_progress = InProgress::ReadingValue {
+                        id,
+                        criticality,
+                        raw_bits: Vec::new(),
+                        segment: Segment::Pending,
+                    };
+                }
+                InProgress::ReadingValue {
+                    id,
+                    criticality,
+                    raw_bits,
+                    segment,
+                } => match segment {
+                    Segment::Pending => {
+                        // Read the whole shape byte in one atomic call, so an `Incomplete`
+                        // here leaves `segment` untouched rather than stranding a
+                        // half-read prefix that a retry would misinterpret.
+                        self.data.decode_align()?;
+                        let shape = self.data.decode_bits_as_integer(8)? as usize;
+                        *segment = if shape & 0x80 == 0 {
+                            Segment::Final(shape & 0x7F)
+                        } else if shape & 0x40 == 0 {
+                            Segment::PendingLength14 {
+                                high: shape & 0x3F,
+                            }
+                        } else {
+                            let m = shape & 0x3F;
+                            if !(1..=4).contains(&m) {
+                                return Err(AperCodecError::new(
+                                    "Length determinant fragment multiplier must be 1 to 4.",
+                                ));
+                            }
+                            Segment::Fragment(m * 16384)
+                        };
+                    }
+                    Segment::PendingLength14 { high } => {
+                        let low = self.data.decode_bits_as_integer(8)? as usize;
+                        *segment = Segment::Final((*high << 8) | low);
+                    }
+                    Segment::Final(0) => {
+                        let id = *id;
+                        let criticality = *criticality;
+                        let raw_bits = std::mem::take(raw_bits);
+                        self.in_progress = InProgress::Start;
+                        self.remaining -= 1;
+                        return Ok(Some((id, criticality, raw_bits)));
+                    }
+                    Segment::Fragment(0) => {
+                        *segment = Segment::Pending;
+                    }
+                    Segment::Final(n) | Segment::Fragment(n) => {
+                        raw_bits.push(self.data.decode_bits_as_integer(8)? as u8);
+                        *n -= 1;
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<'d> Iterator for RecordsDecoder<'d> {
+    type Item = Result<(ProtocolIE_ID, Criticality, Vec<u8>), AperCodecError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_record() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => None,
+            Err(e) => {
+                // Don't keep polling a decoder that's already hit a hard error. An `Incomplete`
+                // keeps the in-progress state, so decoding resumes once more bytes arrive.
+                if !matches!(e, AperCodecError::Incomplete { .. }) {
+                    self.remaining = 0;
+                    self.in_progress = InProgress::Start;
+                }
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Decodes a whole ProtocolIE-Container into [`MaybeParsed`] entries.
+///
+/// `decode_known` maps a recognised `id` to its decoded value; returning
+/// `None` for an `id` it doesn't recognise (rather than an error) normally
+/// keeps that entry around as `MaybeParsed::Unparsed` instead of failing the
+/// whole container — *normally*, because an unrecognised `id` whose
+/// `criticality` is `Reject` fails the container regardless, per the
+/// criticality's own meaning: the procedure can't be safely continued
+/// without understanding that IE. This is the hook the `AperCodec` derive
+/// macro generates for a `SEQUENCE (SIZE(...)) OF ProtocolIE-Field` member.
+pub fn decode_protocol_ie_container<T>(
+    data: &mut AperCodecData,
+    mut decode_known: impl FnMut(ProtocolIE_ID, &[u8]) -> Option<Result<T, AperCodecError>>,
+) -> Result<Vec<MaybeParsed<T>>, AperCodecError> {
+    let mut records = RecordsDecoder::new(data)?;
+    let mut entries = vec![];
+    while let Some((id, criticality, raw_bits)) = records.next_record()? {
+        match decode_known(id, &raw_bits) {
+            Some(Ok(value)) => entries.push(MaybeParsed::Parsed(value)),
+            Some(Err(e)) => return Err(e),
+            None if criticality.is_reject() => {
+                return Err(AperCodecError::new(format!(
+                    "Unrecognised ProtocolIE id {} has Reject criticality",
+                    id.0
+                )));
+            }
+            None => entries.push(MaybeParsed::Unparsed {
+                id,
+                criticality,
+                raw_bits,
+            }),
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A single-element container: count = 1, id = 5, an empty open-type value, and
+    // `criticality` left as the caller's `byte4` so each test can plug in the value it needs.
+    fn one_record_bytes(criticality_byte4: u8) -> [u8; 6] {
+        [0x01, 0x00, 0x00, 0x05, criticality_byte4, 0x00]
+    }
+
+    #[test]
+    fn test_unrecognised_id_is_kept_as_unparsed_for_ignore_and_notify() {
+        for (byte4, expected) in [(0x40u8, Criticality::Ignore), (0x80u8, Criticality::Notify)] {
+            let mut data = AperCodecData::from_slice(&one_record_bytes(byte4));
+            let entries: Vec<MaybeParsed<()>> =
+                decode_protocol_ie_container(&mut data, |_id, _raw| None).unwrap();
+            assert_eq!(
+                entries,
+                vec![MaybeParsed::Unparsed {
+                    id: ProtocolIE_ID(5),
+                    criticality: expected,
+                    raw_bits: vec![],
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn test_unrecognised_id_with_reject_criticality_fails_the_container() {
+        let mut data = AperCodecData::from_slice(&one_record_bytes(0x00));
+        let err =
+            decode_protocol_ie_container::<()>(&mut data, |_id, _raw| None).unwrap_err();
+        assert!(matches!(err, AperCodecError::Error(_)));
+    }
+
+    #[test]
+    fn test_streaming_resumes_mid_record_without_corrupting_the_cursor() {
+        let mut data = AperCodecData::empty();
+        // Only the count and `id` are available so far; `criticality` hasn't arrived yet.
+        data.append(&[0x01, 0x00, 0x00, 0x05]);
+
+        let mut records = RecordsDecoder::new(&mut data).unwrap();
+        let err = records.next_record().unwrap_err();
+        assert_eq!(err, AperCodecError::Incomplete { needed_bits: 2 });
+
+        // The rest of the record arrives: `criticality` = Ignore, then an empty value.
+        records.data_mut().append(&[0x40, 0x00]);
+
+        // Retrying picks up at `criticality`, not back at `id` (which would otherwise
+        // reinterpret these bits as a brand new id and corrupt the rest of the container).
+        let record = records.next_record().unwrap();
+        assert_eq!(
+            record,
+            Some((ProtocolIE_ID(5), Criticality::Ignore, vec![]))
+        );
+        assert_eq!(records.next_record().unwrap(), None);
+    }
+}
@@ -0,0 +1,206 @@
+//! Error type shared by the tokenizer, parser and resolver.
+//!
+//! Every [`Error`] carries an optional [`Span`] pointing at the token that
+//! caused it, and a stack of parser-context frames (innermost first) that
+//! combinators push on their way back out, e.g. `"parsing a constraint"` or
+//! `"in union set"` (`Display` prints each after `while`). This is the same layered-error idea
+//! nom's `VerboseError` uses: the leaf combinator records *what* went wrong
+//! and *where*, and every enclosing combinator adds *why it was there*.
+
+use std::fmt;
+
+use crate::tokenizer::Token;
+
+/// Line/column location of a token in the original ASN.1 source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl From<&Token> for Span {
+    fn from(token: &Token) -> Self {
+        Span {
+            line: token.line,
+            column: token.column,
+        }
+    }
+}
+
+/// An error raised while tokenizing, parsing or resolving an ASN.1 module.
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub message: String,
+    pub span: Option<Span>,
+    pub snippet: Option<String>,
+    /// Parser-context frames, innermost (closest to the failure) first.
+    pub context: Vec<String>,
+}
+
+impl Error {
+    pub fn new(message: String) -> Self {
+        Error {
+            message,
+            span: None,
+            snippet: None,
+            context: vec![],
+        }
+    }
+
+    /// Builds an `Error` anchored at `token`, recording its span and text so
+    /// `Display` can render a caret under the offending token.
+    pub fn at(message: String, token: &Token) -> Self {
+        Error {
+            message,
+            span: Some(Span::from(token)),
+            snippet: Some(token.text.clone()),
+            context: vec![],
+        }
+    }
+
+    /// Pushes a parser-context frame describing the combinator that was
+    /// active when this error bubbled through it.
+    pub fn with_context<S: Into<String>>(mut self, context: S) -> Self {
+        self.context.push(context.into());
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "error: {}", self.message)?;
+        if let (Some(span), Some(snippet)) = (self.span, &self.snippet) {
+            writeln!(f, "  --> line {}, column {}", span.line, span.column)?;
+            writeln!(f, "   | {}", snippet)?;
+            // The snippet is the token's own text, so the caret goes under its first character.
+            writeln!(f, "   | ^")?;
+        }
+        for frame in &self.context {
+            writeln!(f, "  while {}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Collects errors from a whole module parse instead of bailing out on the
+/// first one, so a user can see every problem in a single pass.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorAccumulator {
+    errors: Vec<Error>,
+}
+
+impl ErrorAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, error: Error) {
+        self.errors.push(error);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// Consumes the accumulator, returning `Ok(())` if nothing was recorded
+    /// or `Err(self)` with every error collected along the way.
+    pub fn into_result(self) -> Result<(), ErrorAccumulator> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for ErrorAccumulator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ErrorAccumulator {}
+
+#[macro_export]
+macro_rules! unexpected_token {
+    ($expected:expr, $token:expr) => {
+        $crate::error::Error::at(
+            format!("Expecting {}, Found '{}'", $expected, $token.text),
+            &$token,
+        )
+    };
+}
+
+#[macro_export]
+macro_rules! parse_error {
+    ($msg:expr) => {
+        $crate::error::Error::new($msg.to_string())
+    };
+}
+
+#[macro_export]
+macro_rules! resolve_error {
+    ($msg:expr) => {
+        $crate::error::Error::new($msg.to_string())
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_caret_lands_under_the_offending_column() {
+        let token = Token {
+            text: "Bar".to_string(),
+            line: 3,
+            column: 9,
+        };
+        let error = Error::at("Unknown type reference".to_string(), &token);
+
+        let rendered = error.to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines[1], "  --> line 3, column 9");
+        assert_eq!(lines[2], "   | Bar");
+        // The snippet line holds only the token's text after a 5-character "   | " gutter, so
+        // the caret sits right after the same gutter, under the token's first character.
+        let caret_column = lines[3].find('^').unwrap();
+        assert_eq!(caret_column, "   | ".len());
+    }
+
+    #[test]
+    fn test_display_without_a_span_omits_the_location_block() {
+        let error = Error::new("Something went wrong".to_string());
+        assert_eq!(error.to_string(), "error: Something went wrong\n");
+    }
+
+    #[test]
+    fn test_display_lists_context_frames_innermost_first() {
+        let error = Error::new("bad token".to_string())
+            .with_context("parsing a constraint")
+            .with_context("parsing a type assignment");
+
+        let rendered = error.to_string();
+        let while_lines: Vec<&str> = rendered.lines().filter(|l| l.starts_with("  while")).collect();
+        assert_eq!(
+            while_lines,
+            vec![
+                "  while parsing a constraint",
+                "  while parsing a type assignment",
+            ]
+        );
+    }
+}
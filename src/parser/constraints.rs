@@ -1,6 +1,6 @@
 //! Parser for ASN.1 SubType Constraints
 
-use crate::error::Error;
+use crate::error::{Error, ErrorAccumulator};
 use crate::structs::constraints::{
     Asn1Constraint, Elements, IntersectionSet, RangeElement, SubtypeElements, UnionSet,
     UnionSetElement, ValueElement,
@@ -31,6 +31,49 @@ pub(super) fn parse_constraints<'parser>(
     Ok((constraints, consumed))
 }
 
+// Like `parse_constraints`, except it never bails out on the first bad
+// constraint: every failed attempt is recorded into `errors` and parsing
+// resumes from the next `(` or `,`, so a whole module can be checked in one pass
+// instead of stopping at the first mistake.
+pub(super) fn parse_constraints_accumulating<'parser>(
+    tokens: &'parser [Token],
+    errors: &mut ErrorAccumulator,
+) -> (Vec<Asn1Constraint>, usize) {
+    let mut consumed = 0;
+
+    let mut constraints = vec![];
+    loop {
+        if tokens[consumed..].is_empty() {
+            break;
+        }
+        match parse_constraint(&tokens[consumed..]) {
+            Ok(result) => {
+                constraints.push(result.0);
+                consumed += result.1;
+            }
+            Err(e) => {
+                errors.push(e.with_context("parsing a constraint"));
+                // Skipping exactly one token and retrying would re-fail against the same
+                // broken run of tokens over and over, recording one error per token instead
+                // of one error per actual mistake. Skip ahead to the next `(` (a plausible
+                // start of the following sibling constraint) or `,` (a set-element
+                // separator), whichever comes first, and resume parsing from there.
+                consumed += 1;
+                while consumed < tokens.len()
+                    && !Token::is_round_begin(&tokens[consumed])
+                    && !Token::is_comma(&tokens[consumed])
+                {
+                    consumed += 1;
+                }
+                if consumed < tokens.len() && Token::is_comma(&tokens[consumed]) {
+                    consumed += 1; // `parse_constraint` can't start on a ',', so skip past it too.
+                }
+            }
+        }
+    }
+    (constraints, consumed)
+}
+
 fn parse_constraint<'parser>(tokens: &'parser [Token]) -> Result<(Asn1Constraint, usize), Error> {
     let mut consumed = 0;
 
@@ -39,11 +82,12 @@ fn parse_constraint<'parser>(tokens: &'parser [Token]) -> Result<(Asn1Constraint
     }
     consumed += 1;
 
-    let (root_elements, root_consumed) = parse_union_set(&tokens[consumed..])?;
+    let (root_elements, root_consumed) =
+        parse_union_set(&tokens[consumed..]).map_err(|e| e.with_context("parsing a constraint"))?;
     consumed += root_consumed;
 
     if root_elements.elements.is_empty() {
-        return Err(parse_error!("Empty Set in a Constraint!"));
+        return Err(parse_error!("Empty Set in a Constraint!").with_context("parsing a constraint"));
     }
 
     let mut additional_elements = None;
@@ -52,7 +96,9 @@ fn parse_constraint<'parser>(tokens: &'parser [Token]) -> Result<(Asn1Constraint
 
         // Extension Marker
         if !expect_token(&tokens[consumed..], Token::is_extension)? {
-            return Err(unexpected_token!("'...'", tokens[consumed]));
+            return Err(
+                unexpected_token!("'...'", tokens[consumed]).with_context("parsing a constraint")
+            );
         }
 
         // Potentially Empty additional_elements
@@ -67,7 +113,7 @@ fn parse_constraint<'parser>(tokens: &'parser [Token]) -> Result<(Asn1Constraint
     }
 
     if !expect_token(&tokens[consumed..], Token::is_round_end)? {
-        return Err(unexpected_token!("')'", tokens[consumed]));
+        return Err(unexpected_token!("')'", tokens[consumed]).with_context("parsing a constraint"));
     }
     consumed += 1;
 
@@ -95,9 +141,9 @@ fn parse_union_set<'parser>(tokens: &'parser [Token]) -> Result<(UnionSet, usize
                     iset_elements.push(result.0);
                     consumed += result.1;
                 }
-                Err(_) => {
+                Err(e) => {
                     if expecting_iset {
-                        return Err(parse_error!("Expecting Interesection Set in a Constraint."));
+                        return Err(e.with_context("in union set"));
                     }
                 }
             }
@@ -134,17 +180,22 @@ fn parse_enclosed_union_set<'parser>(tokens: &'parser [Token]) -> Result<(UnionS
     if expect_token(&tokens[consumed..], Token::is_round_begin)? {
         consumed += 1;
 
-        let (union_set, union_set_consumed) = parse_union_set(&tokens[consumed..])?;
+        let (union_set, union_set_consumed) = parse_union_set(&tokens[consumed..])
+            .map_err(|e| e.with_context("in enclosed union set"))?;
         consumed += union_set_consumed;
 
         if !expect_token(&tokens[consumed..], Token::is_round_end)? {
-            return Err(unexpected_token!("')'", tokens[consumed]));
+            return Err(
+                unexpected_token!("')'", tokens[consumed]).with_context("in enclosed union set")
+            );
         } else {
             consumed += 1;
             return Ok((union_set, consumed));
         }
     } else {
-        return Err(unexpected_token!("'('", tokens[consumed]));
+        return Err(
+            unexpected_token!("'('", tokens[consumed]).with_context("in enclosed union set")
+        );
     }
 }
 
@@ -162,7 +213,8 @@ fn parse_intersection_set<'parser>(tokens: &'parser [Token]) -> Result<(Elements
         consumed += 1;
 
         if expect_token(&tokens[consumed..], Token::is_round_begin)? {
-            let (values, values_consumed) = parse_enclosed_union_set(&tokens[consumed..])?;
+            let (values, values_consumed) = parse_enclosed_union_set(&tokens[consumed..])
+                .map_err(|e| e.with_context("in intersection set element"))?;
             consumed += values_consumed;
 
             return Ok((
@@ -172,6 +224,11 @@ fn parse_intersection_set<'parser>(tokens: &'parser [Token]) -> Result<(Elements
         }
     }
 
+    // Each alternative below is tried in turn; we keep the most recent failure so that, if every
+    // alternative fails, the error we surface describes what was actually wrong instead of a bare
+    // "not implemented" message.
+    let mut last_error = None;
+
     // Parse Range Value
     match parse_range_elements(&tokens[consumed..]) {
         Ok(result) => {
@@ -183,12 +240,13 @@ fn parse_intersection_set<'parser>(tokens: &'parser [Token]) -> Result<(Elements
                 consumed,
             ));
         }
-        Err(_) => {}
+        Err(e) => last_error = Some(e),
     }
 
     // Parse nested UnionSet Constraint
     if expect_token(&tokens[consumed..], Token::is_round_begin)? {
-        let (union_set, union_set_consumed) = parse_enclosed_union_set(&tokens[consumed..])?;
+        let (union_set, union_set_consumed) = parse_enclosed_union_set(&tokens[consumed..])
+            .map_err(|e| e.with_context("in intersection set element"))?;
         consumed += union_set_consumed;
 
         return Ok((Elements::ElementSet(union_set), consumed));
@@ -205,7 +263,7 @@ fn parse_intersection_set<'parser>(tokens: &'parser [Token]) -> Result<(Elements
                 consumed,
             ));
         }
-        Err(_) => {}
+        Err(e) => last_error = Some(e),
     }
 
     // Parse ContainedSubtype. Note: While the actual grammar specifies `Type` production, In
@@ -220,10 +278,12 @@ fn parse_intersection_set<'parser>(tokens: &'parser [Token]) -> Result<(Elements
                 consumed,
             ));
         }
-        Err(_) => {}
+        Err(e) => last_error = Some(e),
     }
 
-    Err(parse_error!("parse_intersection_set: Not Implmented"))
+    Err(last_error
+        .unwrap_or_else(|| parse_error!("Expecting a Size, Range, Value or Type"))
+        .with_context("in intersection set element"))
 }
 
 // Parses a Range Value, supports all possible formats.
@@ -246,10 +306,10 @@ fn parse_range_elements<'parser>(tokens: &'parser [Token]) -> Result<(RangeEleme
             if expect_token(&tokens[consumed..], is_min_max_keyword)? {
                 (tokens[consumed].text.clone(), 1)
             } else {
-                return Err(unexpected_token!(
-                    "'MIN', 'MAX' or 'Value'",
-                    tokens[consumed]
-                ));
+                return Err(
+                    unexpected_token!("'MIN', 'MAX' or 'Value'", tokens[consumed])
+                        .with_context("parsing the lower bound of a range"),
+                );
             }
         }
     };
@@ -259,7 +319,9 @@ fn parse_range_elements<'parser>(tokens: &'parser [Token]) -> Result<(RangeEleme
         &tokens[consumed..],
         &[Token::is_less_than, Token::is_range_separator],
     )? {
-        return Err(unexpected_token!("'<' or '..'", tokens[consumed]));
+        return Err(
+            unexpected_token!("'<' or '..'", tokens[consumed]).with_context("in range element")
+        );
     }
 
     let lower_inclusive = if expect_token(&tokens[consumed..], Token::is_less_than)? {
@@ -283,10 +345,10 @@ fn parse_range_elements<'parser>(tokens: &'parser [Token]) -> Result<(RangeEleme
             if expect_token(&tokens[consumed..], is_min_max_keyword)? {
                 (tokens[consumed].text.clone(), 1)
             } else {
-                return Err(unexpected_token!(
-                    "'MIN', 'MAX' or 'Value'",
-                    tokens[consumed]
-                ));
+                return Err(
+                    unexpected_token!("'MIN', 'MAX' or 'Value'", tokens[consumed])
+                        .with_context("parsing the upper bound of a range"),
+                );
             }
         }
     };
@@ -391,4 +453,27 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parse_constraints_accumulating_recovers_once_per_bad_run_not_once_per_token() {
+        // A run of three garbage tokens sits between two otherwise-valid constraints. Skipping
+        // to the next `(` or `,` on failure should resync past the whole run in one error,
+        // instead of re-failing once for each of the three garbage tokens.
+        let reader = std::io::BufReader::new(std::io::Cursor::new(
+            "(1..10) GARBAGE1 GARBAGE2 GARBAGE3 (SIZE(1..5))",
+        ));
+        let tokens = tokenize(reader).unwrap();
+
+        let mut errors = ErrorAccumulator::new();
+        let (constraints, consumed) = parse_constraints_accumulating(&tokens, &mut errors);
+
+        assert_eq!(consumed, tokens.len());
+        assert_eq!(constraints.len(), 2, "{:#?}", constraints);
+        assert_eq!(
+            errors.errors().len(),
+            1,
+            "expected the garbage run to cost exactly one error, got {:#?}",
+            errors.errors()
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Tokenizer for ASN.1 source text.
+//!
+//! Splits raw module text into a flat `Vec<Token>` that the parser consumes.
+//! Each `Token` remembers the line/column it started at so that later parse
+//! errors can point back at the exact spot in the source that caused them.
+
+use std::io::BufRead;
+
+use crate::error::Error;
+
+/// A single lexical token, tagged with its position in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub text: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Token {
+    fn new(text: String, line: usize, column: usize) -> Self {
+        Token { text, line, column }
+    }
+
+    pub fn is_round_begin(token: &Token) -> bool {
+        token.text == "("
+    }
+
+    pub fn is_round_end(token: &Token) -> bool {
+        token.text == ")"
+    }
+
+    pub fn is_comma(token: &Token) -> bool {
+        token.text == ","
+    }
+
+    pub fn is_extension(token: &Token) -> bool {
+        token.text == "..."
+    }
+
+    pub fn is_set_union(token: &Token) -> bool {
+        token.text == "|" || token.text == "UNION"
+    }
+
+    pub fn is_set_intersection(token: &Token) -> bool {
+        token.text == "^" || token.text == "INTERSECTION"
+    }
+
+    pub fn is_less_than(token: &Token) -> bool {
+        token.text == "<"
+    }
+
+    pub fn is_range_separator(token: &Token) -> bool {
+        token.text == ".."
+    }
+
+    pub fn is_given_keyword(token: &Token, keyword: &str) -> bool {
+        token.text == keyword
+    }
+}
+
+/// Tokenizes an entire ASN.1 module, reading it line by line from `reader`.
+///
+/// On a lexical error (e.g. an unterminated string) the returned `Error`
+/// carries the line/column of the offending character.
+pub fn tokenize<R: BufRead>(mut reader: R) -> Result<Vec<Token>, Error> {
+    let mut tokens = vec![];
+
+    let mut line_no = 0;
+    let mut buf = String::new();
+    loop {
+        buf.clear();
+        let read = reader
+            .read_line(&mut buf)
+            .map_err(|e| crate::error::Error::new(format!("IO Error: {}", e)))?;
+        if read == 0 {
+            break;
+        }
+        line_no += 1;
+        tokenize_line(&buf, line_no, &mut tokens)?;
+    }
+
+    Ok(tokens)
+}
+
+fn tokenize_line(line: &str, line_no: usize, tokens: &mut Vec<Token>) -> Result<(), Error> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        // Comments run to the end of the line.
+        if c == '-' && chars.get(i + 1) == Some(&'-') {
+            break;
+        }
+
+        // Quoted strings (cstrings / bstrings / hstrings).
+        if c == '"' || c == '\'' {
+            let quote = c;
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != quote {
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(crate::error::Error::new(format!(
+                    "Unterminated string starting at line {}, column {}",
+                    line_no,
+                    start + 1
+                )));
+            }
+            i += 1; // Consume closing quote.
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Token::new(text, line_no, start + 1));
+            continue;
+        }
+
+        // Multi-character punctuation: '...', '..', '::='.
+        if c == '.' && chars.get(i + 1) == Some(&'.') && chars.get(i + 2) == Some(&'.') {
+            tokens.push(Token::new("...".to_string(), line_no, column));
+            i += 3;
+            continue;
+        }
+        if c == '.' && chars.get(i + 1) == Some(&'.') {
+            tokens.push(Token::new("..".to_string(), line_no, column));
+            i += 2;
+            continue;
+        }
+        if c == ':' && chars.get(i + 1) == Some(&':') && chars.get(i + 2) == Some(&'=') {
+            tokens.push(Token::new("::=".to_string(), line_no, column));
+            i += 3;
+            continue;
+        }
+
+        // Single-character punctuation.
+        if "(){},|^<>.:;=-".contains(c) {
+            tokens.push(Token::new(c.to_string(), line_no, column));
+            i += 1;
+            continue;
+        }
+
+        // Identifiers / keywords / numbers.
+        if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '-')
+            {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Token::new(text, line_no, start + 1));
+            continue;
+        }
+
+        return Err(crate::error::Error::new(format!(
+            "Unexpected character '{}' at line {}, column {}",
+            c, line_no, column
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenize_str(input: &str) -> Vec<Token> {
+        let reader = std::io::BufReader::new(std::io::Cursor::new(input));
+        tokenize(reader).unwrap()
+    }
+
+    #[test]
+    fn test_tokenize_tracks_line_and_column_across_lines() {
+        let tokens = tokenize_str("Foo ::= INTEGER\n    (1..10)\n");
+
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|t| (t.text.as_str(), t.line, t.column))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Foo", 1, 1),
+                ("::=", 1, 5),
+                ("INTEGER", 1, 9),
+                ("(", 2, 5),
+                ("1", 2, 6),
+                ("..", 2, 7),
+                ("10", 2, 9),
+                (")", 2, 11),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_skips_comments_and_blank_lines() {
+        let tokens = tokenize_str("-- a comment\n\nFoo\n");
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|t| (t.text.as_str(), t.line, t.column))
+                .collect::<Vec<_>>(),
+            vec![("Foo", 3, 1)]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_unterminated_string_reports_its_start_position() {
+        let reader = std::io::BufReader::new(std::io::Cursor::new("Foo ::= \"abc\n"));
+        let err = tokenize(reader).unwrap_err();
+        assert!(err.message.contains("line 1, column 9"), "{}", err.message);
+    }
+}